@@ -0,0 +1,222 @@
+//! Fixed- and variable-length framing for zero-copy requests.
+//!
+//! These are [`tokio_util::codec`] `Decoder`/`Encoder` pairs, so callers can
+//! wrap a `quinn` stream's `RecvStream`/`SendStream` in a `FramedRead`/
+//! `FramedWrite` and drive it as a `Stream` of ready-to-dispatch `Request`s,
+//! the same way people frame length-delimited messages with tokio codecs,
+//! just specialized for fixed-width zerocopy structs.
+
+use std::marker::PhantomData;
+use std::{io, mem::size_of};
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+use zerocopy::{AsBytes, BigEndian, FromBytes, Ref, Unaligned, U32};
+
+use crate::{FixedLengthDecode, FixedLengthEncode, OwnedBytes, Request};
+
+/// Decodes a fixed-width `T` into an owned, zero-copy `Request`.
+///
+/// Buffers bytes until at least `size_of::<T>()` (the decoded `Request`'s
+/// [`FixedLengthDecode::LEN`]) are available, then splits exactly
+/// that many off the front of `src` with `BytesMut::split_to`, freezes them
+/// into a `Bytes`, and hands the result to
+/// [`FixedLengthDecode::decode`](crate::FixedLengthDecode::decode) — this
+/// is just the streaming-buffer bookkeeping around that one real
+/// implementation, so no data is copied out of the connection's read buffer
+/// and the parsing logic isn't duplicated.
+pub struct FixedLengthDecoder<T> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> FixedLengthDecoder<T> {
+    pub fn new() -> Self {
+        FixedLengthDecoder { _marker: PhantomData }
+    }
+}
+
+impl<T> Default for FixedLengthDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: FromBytes + Unaligned> Decoder for FixedLengthDecoder<T> {
+    type Item = Request<Ref<OwnedBytes, T>>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let len = <Self::Item as FixedLengthDecode>::LEN;
+        if src.len() < len {
+            return Ok(None);
+        }
+
+        let frame = OwnedBytes(src.split_to(len).freeze());
+        <Self::Item as FixedLengthDecode>::decode(frame)
+            .map(Some)
+            .map_err(Into::into)
+    }
+}
+
+/// Encodes a `Request<K>` by delegating to
+/// [`FixedLengthEncode::encode`](crate::FixedLengthEncode::encode). Used on
+/// the client side to serialize outgoing requests. `K` must itself be
+/// `AsBytes`, so this takes owned keys; a borrowed `&K` is not `AsBytes` and
+/// is not accepted.
+pub struct FixedLengthEncoder<K> {
+    _marker: PhantomData<fn(K)>,
+}
+
+impl<K> FixedLengthEncoder<K> {
+    pub fn new() -> Self {
+        FixedLengthEncoder { _marker: PhantomData }
+    }
+}
+
+impl<K> Default for FixedLengthEncoder<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: AsBytes> Encoder<Request<K>> for FixedLengthEncoder<K> {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Request<K>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.encode(dst);
+        Ok(())
+    }
+}
+
+/// A fixed-width header paired with a variable-length body, for protocols
+/// where the key is followed by a payload rather than being the whole
+/// message.
+pub struct FramedRequest<T> {
+    pub request: Request<Ref<OwnedBytes, T>>,
+    pub body: OwnedBytes,
+}
+
+/// Decodes a fixed-width `T` followed by a `U32<BigEndian>` length prefix and
+/// that many bytes of payload. Like [`FixedLengthDecoder`], it only ever
+/// splits the input buffer, so the header and body both alias the
+/// connection's read buffer instead of being copied.
+pub struct LengthDelimitedDecoder<T> {
+    max_body_len: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> LengthDelimitedDecoder<T> {
+    /// Bodies larger than this are rejected as soon as the length prefix is
+    /// read, before `decode` ever reserves space for them. Without a cap, a
+    /// forged 4-byte length prefix could force a multi-gigabyte allocation
+    /// ahead of any body bytes actually arriving.
+    const DEFAULT_MAX_BODY_LEN: usize = 8 * 1024 * 1024;
+
+    pub fn new() -> Self {
+        Self::with_max_body_len(Self::DEFAULT_MAX_BODY_LEN)
+    }
+
+    pub fn with_max_body_len(max_body_len: usize) -> Self {
+        LengthDelimitedDecoder { max_body_len, _marker: PhantomData }
+    }
+}
+
+impl<T> Default for LengthDelimitedDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: FromBytes + Unaligned> Decoder for LengthDelimitedDecoder<T> {
+    type Item = FramedRequest<T>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let header_len = size_of::<T>();
+        let prefix_len = size_of::<U32<BigEndian>>();
+        if src.len() < header_len + prefix_len {
+            return Ok(None);
+        }
+
+        let body_len = U32::<BigEndian>::read_from(&src[header_len..header_len + prefix_len])
+            .expect("slice is exactly size_of::<U32<BigEndian>>() bytes")
+            .get() as usize;
+        if body_len > self.max_body_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("body length {body_len} exceeds max of {}", self.max_body_len),
+            ));
+        }
+
+        let frame_len = header_len + prefix_len + body_len;
+        if src.len() < frame_len {
+            src.reserve(frame_len - src.len());
+            return Ok(None);
+        }
+
+        let header = OwnedBytes(src.split_to(header_len).freeze());
+        src.advance(prefix_len);
+        let body = OwnedBytes(src.split_to(body_len).freeze());
+
+        let request = Ref::new_unaligned(header)
+            .map(Request)
+            .expect("split_to(size_of::<T>()) always yields a buffer of exactly T's size");
+        Ok(Some(FramedRequest { request, body }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExampleKey;
+    use bytes::BufMut;
+    use zerocopy::U64;
+
+    #[test]
+    fn fixed_length_decoder_waits_for_a_full_frame() {
+        let mut decoder = FixedLengthDecoder::<ExampleKey>::new();
+        let mut buffer = BytesMut::from(&[0u8, 0, 0, 0, 0, 0, 0][..]);
+        assert!(decoder.decode(&mut buffer).unwrap().is_none());
+
+        buffer.put_u8(42);
+        let request = decoder.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(request.0.0.get(), 42);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn fixed_length_round_trips_through_encoder_and_decoder() {
+        let key = ExampleKey(U64::new(7));
+        let mut encoder = FixedLengthEncoder::<ExampleKey>::new();
+        let mut buffer = BytesMut::new();
+        encoder.encode(Request(key.clone()), &mut buffer).unwrap();
+
+        let mut decoder = FixedLengthDecoder::<ExampleKey>::new();
+        let request = decoder.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(*request.0, key);
+    }
+
+    #[test]
+    fn length_delimited_decoder_splits_header_and_body() {
+        let mut buffer = BytesMut::new();
+        buffer.put_u64(9); // ExampleKey header
+        buffer.put_u32(3); // body length prefix
+        buffer.put_slice(b"abc");
+
+        let mut decoder = LengthDelimitedDecoder::<ExampleKey>::new();
+        let framed = decoder.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(framed.request.0.0.get(), 9);
+        assert_eq!(&framed.body.0[..], b"abc");
+    }
+
+    #[test]
+    fn length_delimited_decoder_rejects_a_body_len_over_the_max() {
+        let mut buffer = BytesMut::new();
+        buffer.put_u64(9); // ExampleKey header
+        buffer.put_u32(1024); // body length prefix, over the max below
+
+        let mut decoder = LengthDelimitedDecoder::<ExampleKey>::with_max_body_len(16);
+        let error = decoder.decode(&mut buffer).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
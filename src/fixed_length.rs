@@ -0,0 +1,195 @@
+//! A symmetric, trait-based framing layer.
+//!
+//! [`FixedLengthDecoder`](crate::FixedLengthDecoder) and
+//! [`FixedLengthEncoder`](crate::FixedLengthEncoder) are the `tokio_util`
+//! codec types for framing a byte stream. The traits here are the thing they
+//! hang off of: any wire struct that satisfies zerocopy's `FromBytes`/
+//! `Unaligned`/`AsBytes` bounds gets owned, zero-copy decoding and encoding
+//! for free via the blanket impls below, instead of writing a bespoke
+//! `TryFrom`/`Decoder` pair per type the way `ExampleKey` originally did.
+
+use std::{error, fmt, io, mem::size_of};
+
+use bytes::{BufMut, BytesMut};
+use zerocopy::{AsBytes, FromBytes, Ref, Unaligned};
+
+use crate::{OwnedBytes, Request};
+
+/// Parses a fixed-width wire struct out of an owned buffer without copying.
+pub trait FixedLengthDecode: Sized {
+    /// The wire size of the struct being decoded, in bytes.
+    const LEN: usize;
+
+    fn decode(bytes: OwnedBytes) -> Result<Self, DecodeError>;
+}
+
+/// Serializes a fixed-width wire struct into a buffer.
+pub trait FixedLengthEncode {
+    fn encode(&self, dst: &mut BytesMut);
+}
+
+// Any `T: FromBytes + Unaligned` can be parsed as an owned, zero-copy
+// `Request` with no per-type boilerplate.
+impl<T> FixedLengthDecode for Request<Ref<OwnedBytes, T>>
+where
+    T: FromBytes + Unaligned,
+{
+    const LEN: usize = size_of::<T>();
+
+    fn decode(bytes: OwnedBytes) -> Result<Self, DecodeError> {
+        let actual = bytes.0.len();
+        if actual != Self::LEN {
+            return Err(DecodeError::WrongLength { expected: Self::LEN, actual });
+        }
+        Ref::new_unaligned(bytes)
+            .map(Request)
+            .ok_or(DecodeError::Misaligned)
+    }
+}
+
+// Symmetrically, any `T: AsBytes` can serialize a `Request<T>` for free.
+impl<T> FixedLengthEncode for Request<T>
+where
+    T: AsBytes,
+{
+    fn encode(&self, dst: &mut BytesMut) {
+        let bytes = self.0.as_bytes();
+        dst.reserve(bytes.len());
+        dst.put_slice(bytes);
+    }
+}
+
+/// Why a [`FixedLengthDecode::decode`] call failed.
+#[derive(Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The buffer wasn't exactly `LEN` bytes.
+    WrongLength { expected: usize, actual: usize },
+    /// The buffer was the right length but not validly laid out for the target type.
+    Misaligned,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::WrongLength { expected, actual } => {
+                write!(f, "expected {expected} bytes, got {actual}")
+            }
+            DecodeError::Misaligned => {
+                write!(f, "buffer was not validly laid out for the target type")
+            }
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+// Lets `FixedLengthDecoder` (a `tokio_util::codec::Decoder`, whose `Error`
+// must be an `io::Error`-like type) delegate straight to `decode` without
+// needing its own copy of the validation logic.
+impl From<DecodeError> for io::Error {
+    fn from(error: DecodeError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// Defines a `#[repr(C)]` wire struct with the derives `FixedLengthDecode`
+/// and `FixedLengthEncode` need already applied, so a new protocol struct
+/// doesn't have to repeat `#[derive(AsBytes, FromBytes, Unaligned)]
+/// #[repr(C)]` by hand.
+#[macro_export]
+macro_rules! wire_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(
+            $crate::zerocopy::AsBytes,
+            $crate::zerocopy::FromBytes,
+            $crate::zerocopy::Unaligned,
+            ::std::clone::Clone,
+            ::std::fmt::Debug,
+            ::std::cmp::Eq,
+            ::std::cmp::PartialEq,
+        )]
+        #[repr(C)]
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),*
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire_struct;
+    use zerocopy::{BigEndian, Unaligned, U16, U32, U64};
+
+    wire_struct! {
+        struct Header {
+            id: U64<BigEndian>,
+            flags: U16<BigEndian>,
+        }
+    }
+
+    wire_struct! {
+        struct Ping {
+            nonce: U32<BigEndian>,
+        }
+    }
+
+    #[test]
+    fn decode_rejects_short_buffers() {
+        let bytes = OwnedBytes(bytes::Bytes::from_static(&[0; 4]));
+        let error = Request::<Ref<OwnedBytes, Header>>::decode(bytes).unwrap_err();
+        let expected = <Request<Ref<OwnedBytes, Header>> as FixedLengthDecode>::LEN;
+        assert_eq!(error, DecodeError::WrongLength { expected, actual: 4 });
+    }
+
+    #[test]
+    fn header_round_trips_through_decode_and_encode() {
+        let header = Header { id: U64::new(9000), flags: U16::new(3) };
+        let mut dst = BytesMut::new();
+        Request(header.clone()).encode(&mut dst);
+
+        let decoded = Request::<Ref<OwnedBytes, Header>>::decode(OwnedBytes(dst.freeze())).unwrap();
+        assert_eq!(*decoded.0, header);
+    }
+
+    #[test]
+    fn ping_round_trips_through_decode_and_encode() {
+        let ping = Ping { nonce: U32::new(42) };
+        let mut dst = BytesMut::new();
+        Request(ping.clone()).encode(&mut dst);
+
+        let decoded = Request::<Ref<OwnedBytes, Ping>>::decode(OwnedBytes(dst.freeze())).unwrap();
+        assert_eq!(*decoded.0, ping);
+    }
+
+    // Structs with a trailing slice DST don't have a static `LEN`, so they
+    // can't go through `FixedLengthDecode`; they use the `Ref` zero-copy path
+    // directly instead, the same way `LengthDelimitedDecoder` handles a
+    // header followed by a body.
+    #[derive(AsBytes, FromBytes, Unaligned, Debug, Eq, PartialEq)]
+    #[repr(C)]
+    struct Batch {
+        count: U32<BigEndian>,
+        items: [U32<BigEndian>],
+    }
+
+    #[test]
+    fn trailing_slice_dst_parses_via_ref_without_a_static_len() {
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(&4u32.to_be_bytes());
+        for value in [1u32, 2, 3] {
+            buffer.extend_from_slice(&value.to_be_bytes());
+        }
+
+        let owned = OwnedBytes(buffer.freeze());
+        let batch: Ref<OwnedBytes, Batch> = Ref::new_unaligned(owned).unwrap();
+        assert_eq!(batch.count.get(), 4);
+        assert_eq!(batch.items.iter().map(|v| v.get()).collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+}
@@ -1,46 +1,105 @@
-use std::{convert::Infallible, task::{Context, Poll}};
+use std::{convert::Infallible, ops::Deref, task::{Context, Poll}};
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures_util::future::{Ready, ok};
-use zerocopy::{U64, BigEndian, LayoutVerified, FromBytes, AsBytes, Unaligned};
+use zerocopy::{U64, BigEndian, Ref, ByteSlice, SplitByteSlice, FromBytes, AsBytes, Unaligned};
 use tower_service::Service;
 
+// Re-exported so the `wire_struct!` macro can refer to zerocopy's derives as
+// `$crate::zerocopy::...` regardless of what the caller's crate has in scope.
+pub use zerocopy;
+
+mod client;
+mod codec;
+mod fixed_length;
+mod server;
+
+pub use client::{Client, ClientError, Response};
+pub use codec::{
+    FixedLengthDecoder, FixedLengthEncoder, FramedRequest, LengthDelimitedDecoder,
+};
+pub use fixed_length::{DecodeError, FixedLengthDecode, FixedLengthEncode};
+pub use server::serve;
+
 // The actual Key type is used to perform a lookup in a database elsewhere in the application logic
 #[derive(AsBytes, Clone, Debug, Eq, FromBytes, Unaligned, PartialEq)]
 #[repr(C)]
 pub struct ExampleKey(pub U64<BigEndian>);
 
+// `Bytes` and `BytesMut` are foreign types, so the orphan rule stops us from
+// implementing zerocopy's `ByteSlice` family on them directly. These newtypes
+// give us types we own to implement things on. `OwnedBytes` wraps a `Bytes`
+// and carries the `ByteSlice`/`SplitByteSlice` impls below, so a parsed
+// `Request` can hold on to its backing allocation instead of borrowing it,
+// which is what lets it satisfy `tower::Service::call`'s owned argument.
+// `OwnedBytesMut` only derefs to `[u8]`; it has no `ByteSlice` impls.
+#[derive(Clone, Debug)]
+pub struct OwnedBytes(pub Bytes);
+
+#[derive(Debug)]
+pub struct OwnedBytesMut(pub BytesMut);
+
+impl Deref for OwnedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.0.deref()
+    }
+}
+
+impl Deref for OwnedBytesMut {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.0.deref()
+    }
+}
+
+// SAFETY: `Bytes::deref` is stable for a given value: it always returns a
+// pointer to the same allocation with the same length, which is the
+// invariant `ByteSlice` requires.
+unsafe impl ByteSlice for OwnedBytes {}
+
+// SAFETY: `Bytes::split_to` splits the allocation in place at `mid`, handing
+// back two `Bytes` that together cover exactly the original range with no
+// copy and no overlap, which is what `SplitByteSlice` requires of its split.
+unsafe impl SplitByteSlice for OwnedBytes {
+    fn split_at(mut self, mid: usize) -> Result<(Self, Self), Self> {
+        if mid > self.0.len() {
+            return Err(self);
+        }
+        // `split_to` returns `[0, mid)` and leaves `self` (renamed `rest`
+        // below) holding `[mid, len)`.
+        let prefix = self.0.split_to(mid);
+        let rest = self;
+        Ok((OwnedBytes(prefix), rest))
+    }
+}
+
 // `Request is a wrapper type that implements an internal `FixedLengthDecode`
 // trait for parsing the request from bytes from the network. Here I've just
-// implemented TryFrom<Bytes> as an example. `Request` has a generic `K` because
-// sometimes it is used as a client request and the client might want to pass
-// in an owned `Key` or a borrowed reference to one. The client is generic over
-// any `K: Borrow<Key>`. On the server side though, I'm using the `quinn`
-// library, where requests come in over a `SendStream` as `Bytes` chunks. I want
-// to parse the incoming request without copying the underlying bytes. Also, the
-// server protocol code is seperated from the application logic using the
-// `tower::Service` trait, where the server will use any service
-// `S: Service<Request, Response = Response>` to provide responses to the
-// client. The problem is that the `Service` trait has no room for explicit
-// lifetimes, and the request passed into the `call` method has to be owned.
-// This means that I can't pass `LayoutVerified<&'a [u8], Key>` to the
-// application code. I tried a hack where I had a wrapper struct that contained
-// both the underlying `Bytes` and a `LayoutVerified<&[u8], Key>` which pointed
-// to those bytes, but I really struggled to make the compiler happy with that.
-// That's why I think I want `Bytes` and `BytesMut` from the `bytes` crate to
-// implement `zerocopy::ByteSlice`.
+// implemented TryFrom<OwnedBytes> as an example. `Request` has a generic `K`
+// because sometimes it is used as a client request and the client might want
+// to pass in an owned `Key` or a borrowed reference to one. The client is
+// generic over any `K: Borrow<Key>`. On the server side though, I'm using the
+// `quinn` library, where requests come in over a `RecvStream` as `Bytes`
+// chunks. I want to parse the incoming request without copying the
+// underlying bytes. Also, the server protocol code is seperated from the
+// application logic using the `tower::Service` trait, where the server will
+// use any service `S: Service<Request, Response = Response>` to provide
+// responses to the client. The problem used to be that the `Service` trait
+// has no room for explicit lifetimes, and the request passed into the `call`
+// method has to be owned, so `Ref<&'a [u8], Key>` couldn't be passed to the
+// application code. `OwnedBytes` fixes that: `Ref<OwnedBytes, Key>` owns the
+// allocation it points into, so it's `'static` and can be moved into `call`.
 #[derive(Eq, PartialEq)]
 pub struct Request<K>(pub K);
 
-// Here is the problem:
-
-// I can add an explicit lifetime here, and the compiler won't complain. The 
-// returned request now has a reference to the bytes chunk.
-impl<'a> TryFrom<&'a Bytes> for Request<LayoutVerified<&'a [u8], ExampleKey>> {
-    type Error = (); // Actual error type goes here
+impl TryFrom<OwnedBytes> for Request<Ref<OwnedBytes, ExampleKey>> {
+    type Error = DecodeError;
 
-    fn try_from(bytes: &'a Bytes) -> Result<Self, Self::Error> {
-        LayoutVerified::new_unaligned(bytes.as_ref()).map(Request).ok_or(())
+    fn try_from(bytes: OwnedBytes) -> Result<Self, Self::Error> {
+        FixedLengthDecode::decode(bytes)
     }
 }
 
@@ -48,10 +107,9 @@ struct ExampleResponse;
 
 struct ExampleService;
 
-// Then we have a service that the server will use to make a response to send 
-// back to the client. The compiler will let you elide the explicit lifetime 'a, 
-// but I kept it here for clarity.
-impl<'a> Service<Request<LayoutVerified<&'a [u8], ExampleKey>>> for ExampleService {
+// Now that `Request` owns its bytes, the `Service` impl no longer needs a
+// lifetime parameter at all.
+impl Service<Request<Ref<OwnedBytes, ExampleKey>>> for ExampleService {
     type Response = ExampleResponse;
     type Error = Infallible;
     type Future = Ready<Result<Self::Response, Self::Error>>;
@@ -60,27 +118,48 @@ impl<'a> Service<Request<LayoutVerified<&'a [u8], ExampleKey>>> for ExampleServi
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, _request: Request<LayoutVerified<&'a [u8], ExampleKey>>) -> Self::Future {
+    fn call(&mut self, _request: Request<Ref<OwnedBytes, ExampleKey>>) -> Self::Future {
         // use the request
         // produce a response
         ok(ExampleResponse)
     }
 }
 
-// Here's where the error shows up:
-// The server networking code reads bytes from the connection, parses it, and 
-// passes the request to the service to get a response. Unfortunately, because 
-// the request has a borrowed reference instead of owning the underlying bytes, 
-// the request we pass in doesn't live long enough.
-async fn server_networking_code<'a, S>(mut service: S) 
+// The server networking code reads bytes from the connection, parses it, and
+// passes the request to the service to get a response. Because the request
+// now owns its backing `Bytes`, it's no longer tied to the lifetime of
+// `incoming_request` and can be handed to the service directly.
+async fn server_networking_code<S>(mut service: S)
 where
-    S: Service<Request<LayoutVerified<&'a [u8], ExampleKey>>, Response = ExampleResponse, Error = Infallible>,
+    S: Service<Request<Ref<OwnedBytes, ExampleKey>>, Response = ExampleResponse, Error = Infallible>,
 {
     const REQUEST: &[u8] = &[0xff; 4];
 
     // Accept a connection, read bytes
-    let incoming_request = Bytes::from(REQUEST);
+    let incoming_request = OwnedBytes(Bytes::from(REQUEST));
     // Parse the request by reading bytes from the connection
-    let parsed_request = Request::try_from(&incoming_request).unwrap();
+    let parsed_request = Request::try_from(incoming_request).unwrap();
     let response = service.call(parsed_request).await.unwrap();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Proves the parsed `ExampleKey` still aliases the original allocation
+    // (no copy) even though the `Request` is fully owned: the `Ref`'s
+    // pointer lands inside the range of the `Bytes` we started with.
+    #[test]
+    fn parsed_key_aliases_original_allocation_without_copying() {
+        let backing = Bytes::from(vec![0u8, 0, 0, 0, 0, 0, 0, 42]);
+        let backing_range = backing.as_ptr_range();
+
+        let owned = OwnedBytes(backing);
+        let request = Request::try_from(owned).unwrap();
+        let key: &ExampleKey = &request.0;
+
+        let key_ptr = key as *const ExampleKey as *const u8;
+        assert!(backing_range.start <= key_ptr && key_ptr < backing_range.end);
+        assert_eq!(key.0.get(), 42);
+    }
+}
@@ -0,0 +1,146 @@
+//! A quinn-based server runtime that dispatches framed requests to a cloned
+//! `tower::Service`.
+//!
+//! `tower::Service::call` takes `&mut self`, but connections (and the
+//! streams within a connection) are handled concurrently, so this follows
+//! the standard tower pattern: require `S: Clone` and clone the service once
+//! per accepted stream. Application state that needs to be shared across
+//! connections (the database lookup the original comments mentioned) lives
+//! behind an `Arc` inside the service itself rather than being threaded
+//! through here.
+
+use std::fmt;
+use std::future::poll_fn;
+use std::sync::Arc;
+
+use futures_util::StreamExt;
+use quinn::{Connection, Endpoint, RecvStream, SendStream};
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+use tokio_util::codec::FramedRead;
+use tower_service::Service;
+use zerocopy::{AsBytes, FromBytes, Ref, Unaligned};
+
+use crate::{FixedLengthDecoder, OwnedBytes, Request};
+
+/// Runs the accept loop for `endpoint`, spawning a task per incoming
+/// connection that decodes every framed request on it with
+/// [`FixedLengthDecoder<K>`](crate::FixedLengthDecoder) and dispatches it to
+/// a clone of `service`.
+///
+/// Notifying `shutdown` stops both this loop and every live connection's
+/// own stream-accept loop from accepting anything new; `serve` then waits
+/// for every connection task already in flight to finish before returning,
+/// so no accepted request is dropped mid-flight. Because a live connection
+/// task is also waiting on `shutdown`, call `shutdown.notify_waiters()`
+/// rather than `notify_one()` — there are N+1 concurrent waiters (this loop
+/// plus one per open connection), and `notify_one` would only wake one of
+/// them.
+pub async fn serve<S, K>(endpoint: Endpoint, service: S, shutdown: Arc<Notify>)
+where
+    K: FromBytes + Unaligned + Send + Sync + 'static,
+    S: Service<Request<Ref<OwnedBytes, K>>> + Clone + Send + 'static,
+    S::Response: AsBytes,
+    S::Future: Send,
+    S::Error: fmt::Debug,
+{
+    let mut connections = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            _ = shutdown.notified() => break,
+            accepted = endpoint.accept() => {
+                let Some(connecting) = accepted else { break };
+                let service = service.clone();
+                let shutdown = Arc::clone(&shutdown);
+                connections.spawn(async move {
+                    if let Ok(connection) = connecting.await {
+                        handle_connection(connection, service, shutdown).await;
+                    }
+                });
+            }
+        }
+    }
+
+    // Stop accepting above, then drain every connection that was already in
+    // flight before this future resolves.
+    while connections.join_next().await.is_some() {}
+}
+
+async fn handle_connection<S, K>(connection: Connection, service: S, shutdown: Arc<Notify>)
+where
+    K: FromBytes + Unaligned + Send + Sync + 'static,
+    S: Service<Request<Ref<OwnedBytes, K>>> + Clone + Send + 'static,
+    S::Response: AsBytes,
+    S::Future: Send,
+    S::Error: fmt::Debug,
+{
+    let mut streams = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            _ = shutdown.notified() => break,
+            // Keep draining finished stream tasks off the `JoinSet` as we go
+            // so it doesn't grow unbounded over a long-lived connection.
+            Some(_) = streams.join_next(), if !streams.is_empty() => {}
+            accepted = connection.accept_bi() => {
+                match accepted {
+                    Ok((send, recv)) => {
+                        streams.spawn(handle_stream(send, recv, service.clone()));
+                    }
+                    Err(error) => {
+                        eprintln!("connection closed while accepting a stream: {error}");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // Either the connection is done accepting new streams, or shutdown was
+    // signaled; either way, wait for every stream task already in flight on
+    // it to finish before this task (and so the entry it holds in `serve`'s
+    // `connections` JoinSet) completes.
+    while streams.join_next().await.is_some() {}
+}
+
+async fn handle_stream<S, K>(mut send: SendStream, recv: RecvStream, mut service: S)
+where
+    K: FromBytes + Unaligned,
+    S: Service<Request<Ref<OwnedBytes, K>>>,
+    S::Response: AsBytes,
+    S::Error: fmt::Debug,
+{
+    let mut requests = FramedRead::new(recv, FixedLengthDecoder::<K>::new());
+
+    while let Some(frame) = requests.next().await {
+        let request = match frame {
+            Ok(request) => request,
+            Err(error) => {
+                eprintln!("failed to decode request: {error}");
+                break;
+            }
+        };
+
+        // Standard tower backpressure: don't call a service that isn't
+        // ready to accept another request.
+        if let Err(error) = poll_fn(|context| service.poll_ready(context)).await {
+            eprintln!("service not ready: {error:?}");
+            break;
+        }
+
+        let response = match service.call(request).await {
+            Ok(response) => response,
+            Err(error) => {
+                eprintln!("service call failed: {error:?}");
+                break;
+            }
+        };
+        if let Err(error) = send.write_all(response.as_bytes()).await {
+            eprintln!("failed to write response: {error}");
+            break;
+        }
+    }
+
+    let _ = send.finish();
+}